@@ -1,7 +1,7 @@
 // Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
 // Licensed under the Apache License, Version 2.0 (see LICENSE).
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::Path;
 
@@ -11,19 +11,138 @@ use toml::Value;
 use super::id::{NameTransform, OptionId};
 use super::{ListEdit, ListEditAction, OptionsSource};
 
+/// The on-disk format of a config file, detected from its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConfigFormat {
+  Toml,
+  Json,
+  Yaml,
+}
+
+impl ConfigFormat {
+  fn from_path<P: AsRef<Path>>(file: P) -> Result<ConfigFormat, String> {
+    match file.as_ref().extension().and_then(|ext| ext.to_str()) {
+      Some("toml") => Ok(ConfigFormat::Toml),
+      Some("json") => Ok(ConfigFormat::Json),
+      Some("yaml") | Some("yml") => Ok(ConfigFormat::Yaml),
+      Some(ext) => Err(format!(
+        "Unrecognized config file extension `{}` for {}: expected one of `toml`, `json`, `yaml`, `yml`.",
+        ext,
+        file.as_ref().display()
+      )),
+      None => Err(format!(
+        "Config file {} has no extension to infer its format from.",
+        file.as_ref().display()
+      )),
+    }
+  }
+
+  fn parse(&self, contents: &str) -> Result<Value, String> {
+    match self {
+      ConfigFormat::Toml => contents.parse::<Value>().map_err(|e| e.to_string()),
+      ConfigFormat::Json => {
+        let json: serde_json::Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        json_to_toml(json)
+      }
+      ConfigFormat::Yaml => {
+        let yaml: serde_yaml::Value = serde_yaml::from_str(contents).map_err(|e| e.to_string())?;
+        yaml_to_toml(yaml)
+      }
+    }
+  }
+}
+
+fn json_to_toml(value: serde_json::Value) -> Result<Value, String> {
+  Ok(match value {
+    serde_json::Value::Null => {
+      return Err("TOML has no null value, but the config contained one.".to_owned())
+    }
+    serde_json::Value::Bool(b) => Value::Boolean(b),
+    serde_json::Value::Number(n) => {
+      if let Some(i) = n.as_i64() {
+        Value::Integer(i)
+      } else if let Some(f) = n.as_f64() {
+        Value::Float(f)
+      } else {
+        return Err(format!("Number {} is out of range for a TOML value.", n));
+      }
+    }
+    serde_json::Value::String(s) => Value::String(s),
+    serde_json::Value::Array(items) => {
+      Value::Array(items.into_iter().map(json_to_toml).collect::<Result<_, _>>()?)
+    }
+    serde_json::Value::Object(map) => {
+      let mut table = Table::new();
+      for (k, v) in map {
+        table.insert(k, json_to_toml(v)?);
+      }
+      Value::Table(table)
+    }
+  })
+}
+
+fn yaml_to_toml(value: serde_yaml::Value) -> Result<Value, String> {
+  Ok(match value {
+    serde_yaml::Value::Null => {
+      return Err("TOML has no null value, but the config contained one.".to_owned())
+    }
+    serde_yaml::Value::Bool(b) => Value::Boolean(b),
+    serde_yaml::Value::Number(n) => {
+      if let Some(i) = n.as_i64() {
+        Value::Integer(i)
+      } else if let Some(f) = n.as_f64() {
+        Value::Float(f)
+      } else {
+        return Err(format!("Number {} is out of range for a TOML value.", n));
+      }
+    }
+    serde_yaml::Value::String(s) => Value::String(s),
+    serde_yaml::Value::Sequence(items) => {
+      Value::Array(items.into_iter().map(yaml_to_toml).collect::<Result<_, _>>()?)
+    }
+    serde_yaml::Value::Mapping(map) => {
+      let mut table = Table::new();
+      for (k, v) in map {
+        let key = match k {
+          serde_yaml::Value::String(s) => s,
+          other => {
+            return Err(format!(
+              "Expected a string key in a YAML config, but found {:?}.",
+              other
+            ))
+          }
+        };
+        table.insert(key, yaml_to_toml(v)?);
+      }
+      Value::Table(table)
+    }
+    serde_yaml::Value::Tagged(tagged) => yaml_to_toml(tagged.value)?,
+  })
+}
+
 #[derive(Clone)]
 pub(crate) struct Config {
   config: Value,
+  // Seed values (e.g. `buildroot`) available to `%(name)s` interpolation.
+  seed_values: BTreeMap<String, String>,
 }
 
 impl Config {
   pub(crate) fn default() -> Config {
     Config {
       config: Value::Table(Table::new()),
+      seed_values: BTreeMap::new(),
     }
   }
 
+  /// Attaches the seed values used to resolve `%(name)s` placeholders.
+  pub(crate) fn with_seed_values(mut self, seed_values: BTreeMap<String, String>) -> Config {
+    self.seed_values = seed_values;
+    self
+  }
+
   pub(crate) fn parse<P: AsRef<Path>>(file: P) -> Result<Config, String> {
+    let format = ConfigFormat::from_path(&file)?;
     let config_contents = fs::read_to_string(&file).map_err(|e| {
       format!(
         "Failed to read config file {}: {}",
@@ -31,7 +150,7 @@ impl Config {
         e
       )
     })?;
-    let config = config_contents.parse::<Value>().map_err(|e| {
+    let config = format.parse(&config_contents).map_err(|e| {
       format!(
         "Failed to parse config file {}: {}",
         file.as_ref().display(),
@@ -39,7 +158,10 @@ impl Config {
       )
     })?;
     if config.is_table() {
-      Ok(Config { config })
+      Ok(Config {
+        config,
+        seed_values: BTreeMap::new(),
+      })
     } else {
       Err(format!(
         "Expected the config file {} to contain a table but contained a {}: {}",
@@ -63,6 +185,76 @@ impl Config {
     id.name("_", NameTransform::None)
   }
 
+  /// Descends into the config's value tree along a dotted, optionally-indexed path such as
+  /// `a.b.c` or `a.b[0]`.
+  pub(crate) fn get_value_at_path(&self, path: &str) -> Result<Option<&Value>, String> {
+    let mut current = &self.config;
+    for segment in path.split('.') {
+      let (name, indices) = Self::parse_path_segment(segment)?;
+      current = match current.get(name) {
+        Some(value) => value,
+        None => return Ok(None),
+      };
+      for index in indices {
+        let array = current.as_array().ok_or_else(|| {
+          format!(
+            "Expected {} to be an array to index into with [{}] in path {:?}, but given {}.",
+            name, index, path, current
+          )
+        })?;
+        current = match array.get(index) {
+          Some(value) => value,
+          None => return Ok(None),
+        };
+      }
+    }
+    Ok(Some(current))
+  }
+
+  // Splits `b[0][1]` into its name (`b`) and indices (`[0, 1]`).
+  fn parse_path_segment(segment: &str) -> Result<(&str, Vec<usize>), String> {
+    let name_end = segment.find('[').unwrap_or(segment.len());
+    let name = &segment[..name_end];
+    let mut rest = &segment[name_end..];
+    let mut indices = vec![];
+    while !rest.is_empty() {
+      if !rest.starts_with('[') {
+        return Err(format!(
+          "Malformed path segment {:?}: expected `[` to start an array index.",
+          segment
+        ));
+      }
+      let close = rest.find(']').ok_or_else(|| {
+        format!("Malformed path segment {:?}: missing a closing `]`.", segment)
+      })?;
+      let index_str = &rest[1..close];
+      let index = index_str.parse::<usize>().map_err(|e| {
+        format!(
+          "Malformed array index `{}` in path segment {:?}: {}",
+          index_str, segment, e
+        )
+      })?;
+      indices.push(index);
+      rest = &rest[close + 1..];
+    }
+    Ok((name, indices))
+  }
+
+  /// Deserializes an entire scope's table into a strongly-typed `T`.
+  pub(crate) fn deserialize_scope<T: serde::de::DeserializeOwned>(
+    &self,
+    scope: &str,
+  ) -> Result<T, String> {
+    let table = self
+      .config
+      .get(scope)
+      .cloned()
+      .unwrap_or_else(|| Value::Table(Table::new()));
+    table
+      .try_into::<T>()
+      .map_err(|e| format!("Failed to deserialize the `{}` scope: {}", scope, e))
+  }
+
   fn extract_string_list(option_name: &str, value: &Value) -> Result<Vec<String>, String> {
     if let Some(array) = value.as_array() {
       let mut items = vec![];
@@ -92,20 +284,277 @@ impl Config {
       .and_then(|table| table.get(Self::option_name(id)))
   }
 
+  // Resolves `%(name)s`-style placeholders in `raw`. `visited` detects cyclic references.
+  fn interpolate(&self, option_name: &str, raw: &str, visited: &mut HashSet<String>) -> Result<String, String> {
+    let mut result = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+      if c != '%' {
+        result.push(c);
+        continue;
+      }
+      match chars.peek() {
+        Some('%') => {
+          chars.next();
+          result.push('%');
+        }
+        Some('(') => {
+          chars.next();
+          let mut name = String::new();
+          let mut closed = false;
+          for c2 in chars.by_ref() {
+            if c2 == ')' {
+              closed = true;
+              break;
+            }
+            name.push(c2);
+          }
+          if !closed {
+            return Err(format!(
+              "Unterminated %({} placeholder in {}: expected a closing `)`.",
+              name, option_name
+            ));
+          }
+          if chars.next_if_eq(&'s').is_none() {
+            return Err(format!(
+              "Expected %({})s in {}: placeholders must end with `s`.",
+              name, option_name
+            ));
+          }
+          if !visited.insert(name.clone()) {
+            return Err(format!(
+              "Cyclic reference to `{}` while interpolating {}.",
+              name, option_name
+            ));
+          }
+          let value = self.resolve_placeholder(&name).ok_or_else(|| {
+            format!(
+              "Unknown value `{}` referenced by %({})s in {}.",
+              name, name, option_name
+            )
+          })?;
+          let resolved = self.interpolate(option_name, &value, visited)?;
+          visited.remove(&name);
+          result.push_str(&resolved);
+        }
+        _ => result.push('%'),
+      }
+    }
+    Ok(result)
+  }
+
+  fn resolve_placeholder(&self, name: &str) -> Option<String> {
+    if let Some(value) = self.seed_values.get(name) {
+      return Some(value.to_owned());
+    }
+    self
+      .config
+      .get("DEFAULT")
+      .and_then(|table| table.get(name))
+      .and_then(|value| value.as_str())
+      .map(|s| s.to_owned())
+  }
+
   pub(crate) fn merge(self, other: Config) -> Config {
-    let mut map = self.config.as_table().unwrap().to_owned();
-    map.extend(
-      other
-        .config
-        .as_table()
-        .unwrap()
-        .iter()
-        .map(|(k, v)| (k.to_owned(), v.to_owned())),
+    let mut seed_values = self.seed_values;
+    seed_values.extend(other.seed_values);
+    let merged = Self::merge_tables(
+      self.config.as_table().unwrap().to_owned(),
+      other.config.as_table().unwrap().to_owned(),
     );
     Config {
-      config: Value::Table(map),
+      config: Value::Table(merged),
+      seed_values,
     }
   }
+
+  // Recursively merges `other` into `base`, merging sub-tables key-by-key instead of replacing
+  // them wholesale.
+  fn merge_tables(mut base: Table, other: Table) -> Table {
+    for (key, other_value) in other {
+      match (base.get(&key), &other_value) {
+        (Some(Value::Table(base_table)), Value::Table(other_table)) => {
+          let merged_sub_table = Self::merge_tables(base_table.to_owned(), other_table.to_owned());
+          base.insert(key, Value::Table(merged_sub_table));
+        }
+        _ => {
+          base.insert(key, other_value);
+        }
+      }
+    }
+    base
+  }
+}
+
+#[cfg(test)]
+mod path_tests {
+  use super::*;
+
+  fn config_from_toml(toml: &str) -> Config {
+    Config {
+      config: toml.parse::<Value>().unwrap(),
+      seed_values: BTreeMap::new(),
+    }
+  }
+
+  #[test]
+  fn dotted_path() {
+    let config = config_from_toml("[a]\nb = { c = 5 }\n");
+    assert_eq!(
+      config.get_value_at_path("a.b.c").unwrap(),
+      Some(&Value::Integer(5))
+    );
+  }
+
+  #[test]
+  fn indexed_path() {
+    let config = config_from_toml("[a]\nb = [1, 2, 3]\n");
+    assert_eq!(
+      config.get_value_at_path("a.b[1]").unwrap(),
+      Some(&Value::Integer(2))
+    );
+  }
+
+  #[test]
+  fn chained_indices() {
+    let config = config_from_toml("[a]\nb = [[1, 2], [3, 4]]\n");
+    assert_eq!(
+      config.get_value_at_path("a.b[1][0]").unwrap(),
+      Some(&Value::Integer(3))
+    );
+  }
+
+  #[test]
+  fn missing_segment_is_none() {
+    let config = config_from_toml("[a]\nb = 1\n");
+    assert_eq!(config.get_value_at_path("a.missing").unwrap(), None);
+  }
+
+  #[test]
+  fn out_of_range_index_is_none() {
+    let config = config_from_toml("[a]\nb = [1, 2]\n");
+    assert_eq!(config.get_value_at_path("a.b[5]").unwrap(), None);
+  }
+
+  #[test]
+  fn indexing_a_non_array_errors() {
+    let config = config_from_toml("[a]\nb = 1\n");
+    let err = config.get_value_at_path("a.b[0]").unwrap_err();
+    assert!(err.contains("Expected b to be an array"), "{}", err);
+  }
+
+  #[test]
+  fn parse_path_segment_with_no_indices() {
+    assert_eq!(Config::parse_path_segment("b").unwrap(), ("b", vec![]));
+  }
+
+  #[test]
+  fn parse_path_segment_with_multiple_indices() {
+    assert_eq!(
+      Config::parse_path_segment("b[0][12]").unwrap(),
+      ("b", vec![0, 12])
+    );
+  }
+
+  #[test]
+  fn parse_path_segment_missing_closing_bracket() {
+    let err = Config::parse_path_segment("b[0").unwrap_err();
+    assert!(err.contains("missing a closing `]`"), "{}", err);
+  }
+
+  #[test]
+  fn parse_path_segment_non_numeric_index() {
+    let err = Config::parse_path_segment("b[x]").unwrap_err();
+    assert!(err.contains("Malformed array index"), "{}", err);
+  }
+
+  #[test]
+  fn parse_path_segment_missing_opening_bracket() {
+    let err = Config::parse_path_segment("b[0]1]").unwrap_err();
+    assert!(err.contains("expected `[` to start an array index"), "{}", err);
+  }
+}
+
+#[cfg(test)]
+mod interpolate_tests {
+  use super::*;
+
+  fn config_from_toml(toml: &str) -> Config {
+    Config {
+      config: toml.parse::<Value>().unwrap(),
+      seed_values: BTreeMap::new(),
+    }
+  }
+
+  #[test]
+  fn escaped_percent_is_not_a_placeholder() {
+    let config = config_from_toml("");
+    let resolved = config
+      .interpolate("opt", "100%% done", &mut HashSet::new())
+      .unwrap();
+    assert_eq!(resolved, "100% done");
+  }
+
+  #[test]
+  fn resolves_from_seed_values() {
+    let mut seed_values = BTreeMap::new();
+    seed_values.insert("buildroot".to_owned(), "/repo".to_owned());
+    let config = Config {
+      config: "".parse::<Value>().unwrap(),
+      seed_values,
+    };
+    let resolved = config
+      .interpolate("opt", "%(buildroot)s/src", &mut HashSet::new())
+      .unwrap();
+    assert_eq!(resolved, "/repo/src");
+  }
+
+  #[test]
+  fn resolves_nested_placeholders_in_default_scope() {
+    let config = config_from_toml(
+      "[DEFAULT]\nbuildroot = \"/repo\"\nsrc_root = \"%(buildroot)s/src\"\n",
+    );
+    let resolved = config
+      .interpolate("opt", "%(src_root)s/main.rs", &mut HashSet::new())
+      .unwrap();
+    assert_eq!(resolved, "/repo/src/main.rs");
+  }
+
+  #[test]
+  fn detects_cyclic_reference() {
+    let config = config_from_toml("[DEFAULT]\na = \"%(b)s\"\nb = \"%(a)s\"\n");
+    let err = config
+      .interpolate("opt", "%(a)s", &mut HashSet::new())
+      .unwrap_err();
+    assert!(err.contains("Cyclic reference"), "{}", err);
+  }
+
+  #[test]
+  fn unknown_placeholder_errors() {
+    let config = config_from_toml("");
+    let err = config
+      .interpolate("opt", "%(missing)s", &mut HashSet::new())
+      .unwrap_err();
+    assert!(err.contains("Unknown value"), "{}", err);
+  }
+
+  #[test]
+  fn placeholder_missing_trailing_s_errors() {
+    let config = config_from_toml("[DEFAULT]\na = \"x\"\n");
+    let err = config
+      .interpolate("opt", "%(a)", &mut HashSet::new())
+      .unwrap_err();
+    assert!(err.contains("placeholders must end with `s`"), "{}", err);
+  }
+
+  #[test]
+  fn unterminated_placeholder_errors() {
+    let config = config_from_toml("");
+    let err = config
+      .interpolate("opt", "%(a", &mut HashSet::new())
+      .unwrap_err();
+    assert!(err.contains("Unterminated"), "{}", err);
+  }
 }
 
 impl OptionsSource for Config {
@@ -116,7 +565,8 @@ impl OptionsSource for Config {
   fn get_string(&self, id: &OptionId) -> Result<Option<String>, String> {
     if let Some(value) = self.get_value(id) {
       if let Some(string) = value.as_str() {
-        Ok(Some(string.to_owned()))
+        let option_name = format!("{}", id);
+        Ok(Some(self.interpolate(&option_name, string, &mut HashSet::new())?))
       } else {
         Err(format!(
           "Expected {} to be a string but given {}.",
@@ -155,6 +605,32 @@ impl OptionsSource for Config {
     }
   }
 
+  fn get_int(&self, id: &OptionId) -> Result<Option<i64>, String> {
+    if let Some(value) = self.get_value(id) {
+      if let Some(int) = value.as_integer() {
+        Ok(Some(int))
+      } else {
+        Err(format!("Expected {} to be an int but given {}.", id, value))
+      }
+    } else {
+      Ok(None)
+    }
+  }
+
+  fn get_dict(&self, id: &OptionId) -> Result<Option<BTreeMap<String, Value>>, String> {
+    if let Some(value) = self.get_value(id) {
+      if let Some(table) = value.as_table() {
+        Ok(Some(
+          table.iter().map(|(k, v)| (k.to_owned(), v.to_owned())).collect(),
+        ))
+      } else {
+        Err(format!("Expected {} to be a table but given {}.", id, value))
+      }
+    } else {
+      Ok(None)
+    }
+  }
+
   fn get_string_list(&self, id: &OptionId) -> Result<Option<Vec<ListEdit<String>>>, String> {
     if let Some(table) = self.config.get(&id.scope()) {
       let option_name = Self::option_name(id);
@@ -193,6 +669,11 @@ impl OptionsSource for Config {
         }
       }
       if !list_edits.is_empty() {
+        for list_edit in &mut list_edits {
+          for item in &mut list_edit.items {
+            *item = self.interpolate(&option_name, item, &mut HashSet::new())?;
+          }
+        }
         return Ok(Some(list_edits));
       }
     }
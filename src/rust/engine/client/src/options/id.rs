@@ -0,0 +1,36 @@
+// Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum NameTransform {
+  None,
+  ToLower,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct OptionId {
+  scope: String,
+  components: Vec<String>,
+}
+
+impl OptionId {
+  pub(crate) fn scope(&self) -> String {
+    self.scope.clone()
+  }
+
+  pub(crate) fn name(&self, sep: &str, transform: NameTransform) -> String {
+    let joined = self.components.join(sep);
+    match transform {
+      NameTransform::None => joined,
+      NameTransform::ToLower => joined.to_lowercase(),
+    }
+  }
+}
+
+impl fmt::Display for OptionId {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "--{}", self.name("-", NameTransform::ToLower))
+  }
+}
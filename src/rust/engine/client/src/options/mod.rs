@@ -0,0 +1,32 @@
+// Copyright 2021 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::collections::BTreeMap;
+
+use toml::Value;
+
+pub(crate) mod config;
+pub(crate) mod id;
+
+use id::OptionId;
+
+pub(crate) enum ListEditAction {
+  Replace,
+  Add,
+  Remove,
+}
+
+pub(crate) struct ListEdit<T> {
+  pub(crate) action: ListEditAction,
+  pub(crate) items: Vec<T>,
+}
+
+pub(crate) trait OptionsSource {
+  fn display(&self, id: &OptionId) -> String;
+  fn get_string(&self, id: &OptionId) -> Result<Option<String>, String>;
+  fn get_bool(&self, id: &OptionId) -> Result<Option<bool>, String>;
+  fn get_float(&self, id: &OptionId) -> Result<Option<f64>, String>;
+  fn get_int(&self, id: &OptionId) -> Result<Option<i64>, String>;
+  fn get_dict(&self, id: &OptionId) -> Result<Option<BTreeMap<String, Value>>, String>;
+  fn get_string_list(&self, id: &OptionId) -> Result<Option<Vec<ListEdit<String>>>, String>;
+}
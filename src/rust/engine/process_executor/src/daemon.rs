@@ -0,0 +1,210 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use hashing::{Digest, Fingerprint};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use workunit_store::{in_workunit, Level};
+
+/// Default location of the Unix socket a `--daemon` listens on, unless overridden by
+/// `--daemon-socket`.
+pub fn default_socket_path() -> PathBuf {
+  std::env::temp_dir().join("process_executor.sock")
+}
+
+/// The request payload sent by a client to a running `--daemon`, one JSON object per line.
+/// Mirrors the flat-args fields of `CommandSpec` that `make_request_from_flat_args` consumes,
+/// except `cache_key_gen_version`, which is not carried.
+#[derive(Serialize, Deserialize)]
+pub struct DaemonRequest {
+  pub argv: Vec<String>,
+  pub env: Vec<String>,
+  pub input_digest: Fingerprint,
+  pub input_digest_length: usize,
+  pub output_file_path: Vec<PathBuf>,
+  pub output_directory_path: Vec<PathBuf>,
+  pub working_directory: Option<PathBuf>,
+  pub jdk: Option<PathBuf>,
+  pub concurrency_available: Option<usize>,
+  pub priority: Option<i32>,
+}
+
+/// The response a `--daemon` sends back for a single `DaemonRequest`.
+#[derive(Serialize, Deserialize)]
+pub struct DaemonResponse {
+  pub exit_code: i32,
+  pub stdout_digest_fingerprint: String,
+  pub stdout_digest_length: usize,
+  pub stderr_digest_fingerprint: String,
+  pub stderr_digest_length: usize,
+  pub output_directory_fingerprint: String,
+  pub output_directory_length: usize,
+  pub execution_strategy: String,
+  pub metadata_source: String,
+  pub metadata_total_elapsed_ms: Option<u64>,
+}
+
+/// Binds `socket_path` and serves `DaemonRequest`s against an already-constructed `Store` and
+/// `CommandRunner` until the process is killed, amortizing store/channel warmup across many
+/// small actions instead of paying it per-invocation.
+pub async fn serve(
+  socket_path: &Path,
+  store: store::Store,
+  runner: Arc<dyn process_execution::CommandRunner>,
+  execution_strategy: &'static str,
+) -> Result<(), String> {
+  if socket_path.exists() {
+    std::fs::remove_file(socket_path)
+      .map_err(|e| format!("Failed to remove stale socket {}: {}", socket_path.display(), e))?;
+  }
+  let listener = UnixListener::bind(socket_path)
+    .map_err(|e| format!("Failed to bind daemon socket {}: {}", socket_path.display(), e))?;
+  log::info!("process_executor daemon listening on {}", socket_path.display());
+
+  loop {
+    let (stream, _) = listener
+      .accept()
+      .await
+      .map_err(|e| format!("Failed to accept daemon connection: {}", e))?;
+    let store = store.clone();
+    let runner = runner.clone();
+    tokio::spawn(async move {
+      if let Err(e) = handle_connection(stream, store, runner, execution_strategy).await {
+        log::warn!("process_executor daemon request failed: {}", e);
+      }
+    });
+  }
+}
+
+async fn handle_connection(
+  mut stream: UnixStream,
+  store: store::Store,
+  runner: Arc<dyn process_execution::CommandRunner>,
+  execution_strategy: &'static str,
+) -> Result<(), String> {
+  let (reader, mut writer) = stream.split();
+  let mut line = String::new();
+  BufReader::new(reader)
+    .read_line(&mut line)
+    .await
+    .map_err(|e| format!("Failed to read daemon request: {}", e))?;
+
+  let request: DaemonRequest =
+    serde_json::from_str(&line).map_err(|e| format!("Failed to parse daemon request: {}", e))?;
+
+  let input_digests = process_execution::InputDigests::new(
+    &store,
+    fs::DirectoryDigest::from_persisted_digest(Digest::new(
+      request.input_digest,
+      request.input_digest_length,
+    )),
+    std::collections::BTreeMap::default(),
+    std::collections::BTreeSet::default(),
+  )
+  .await
+  .map_err(|e| format!("Could not create input digest for daemon request: {:?}", e))?;
+
+  let process = process_execution::Process {
+    argv: request.argv,
+    env: super::collection_from_keyvalues(request.env.iter()),
+    working_directory: request
+      .working_directory
+      .map(fs::RelativePath::new)
+      .transpose()
+      .map_err(|e| format!("working-directory must be a relative path: {:?}", e))?,
+    input_digests,
+    output_files: request
+      .output_file_path
+      .iter()
+      .map(fs::RelativePath::new)
+      .collect::<Result<_, _>>()?,
+    output_directories: request
+      .output_directory_path
+      .iter()
+      .map(fs::RelativePath::new)
+      .collect::<Result<_, _>>()?,
+    timeout: Some(std::time::Duration::new(15 * 60, 0)),
+    description: "process_executor --daemon".to_string(),
+    level: Level::Info,
+    append_only_caches: std::collections::BTreeMap::new(),
+    jdk_home: request.jdk,
+    platform: process_execution::Platform::current().unwrap(),
+    execution_slot_variable: None,
+    concurrency_available: request.concurrency_available.unwrap_or(0),
+    cache_scope: process_execution::ProcessCacheScope::Always,
+    execution_strategy: process_execution::ProcessExecutionStrategy::Local,
+    remote_cache_speculation_delay: std::time::Duration::from_millis(0),
+    priority: request.priority.unwrap_or(0),
+  };
+
+  let result = in_workunit!(
+    "process_executor_daemon_request",
+    Level::Info,
+    |workunit| async move {
+      runner
+        .run(process_execution::Context::default(), workunit, process)
+        .await
+    }
+  )
+  .await
+  .map_err(|e| format!("Error executing daemon request: {}", e))?;
+
+  let response = DaemonResponse {
+    exit_code: result.exit_code,
+    stdout_digest_fingerprint: result.stdout_digest.hash.to_hex(),
+    stdout_digest_length: result.stdout_digest.size_bytes,
+    stderr_digest_fingerprint: result.stderr_digest.hash.to_hex(),
+    stderr_digest_length: result.stderr_digest.size_bytes,
+    output_directory_fingerprint: result.output_directory.as_digest().hash.to_hex(),
+    output_directory_length: result.output_directory.as_digest().size_bytes,
+    execution_strategy: execution_strategy.to_owned(),
+    metadata_source: format!("{:?}", result.metadata.source),
+    metadata_total_elapsed_ms: result.metadata.total_elapsed.map(|d| d.as_millis() as u64),
+  };
+  let response_line = serde_json::to_string(&response)
+    .map_err(|e| format!("Failed to serialize daemon response: {}", e))?;
+  writer
+    .write_all(response_line.as_bytes())
+    .await
+    .map_err(|e| format!("Failed to write daemon response: {}", e))?;
+  writer
+    .write_all(b"\n")
+    .await
+    .map_err(|e| format!("Failed to write daemon response: {}", e))?;
+  Ok(())
+}
+
+/// Submits a single request to a running `--daemon` at `socket_path`, so that the caller need not
+/// construct a fresh `CommandRunner` for a one-shot invocation.
+pub async fn submit(socket_path: &Path, request: DaemonRequest) -> Result<DaemonResponse, String> {
+  let mut stream = UnixStream::connect(socket_path).await.map_err(|e| {
+    format!(
+      "Failed to connect to process_executor daemon at {}: {}",
+      socket_path.display(),
+      e
+    )
+  })?;
+
+  let request_line = serde_json::to_string(&request)
+    .map_err(|e| format!("Failed to serialize daemon request: {}", e))?;
+  stream
+    .write_all(request_line.as_bytes())
+    .await
+    .map_err(|e| format!("Failed to write daemon request: {}", e))?;
+  stream
+    .write_all(b"\n")
+    .await
+    .map_err(|e| format!("Failed to write daemon request: {}", e))?;
+
+  let mut line = String::new();
+  BufReader::new(stream)
+    .read_line(&mut line)
+    .await
+    .map_err(|e| format!("Failed to read daemon response: {}", e))?;
+
+  serde_json::from_str(&line).map_err(|e| format!("Failed to parse daemon response: {}", e))
+}
@@ -0,0 +1,59 @@
+// Copyright 2024 Pants project contributors (see CONTRIBUTORS.md).
+// Licensed under the Apache License, Version 2.0 (see LICENSE).
+
+use std::fs::{self, File, OpenOptions};
+use std::path::Path;
+
+use fs4::FileExt;
+
+/// An advisory lock on a `.lock` file inside a directory, used to coordinate multiple
+/// `process_executor` invocations that share a `--local-store-path` or `--named-cache-path`.
+/// The lock is released when the guard is dropped.
+pub struct DirLock {
+  file: File,
+}
+
+impl DirLock {
+  /// Acquires a shared lock on `dir`, blocking until it is available. Use this for ordinary
+  /// reads/writes, which may proceed concurrently with one another.
+  pub fn shared(dir: &Path) -> Result<DirLock, String> {
+    let file = Self::open(dir)?;
+    file
+      .lock_shared()
+      .map_err(|e| format!("Failed to acquire shared lock on {}: {}", dir.display(), e))?;
+    Ok(DirLock { file })
+  }
+
+  /// Acquires an exclusive lock on `dir`, blocking until it is available. Use this for
+  /// operations that must not run concurrently with any other access, such as populating a
+  /// named cache or immutable input directory, or GC/compaction: a shared lock only keeps
+  /// writers out of a reader's way, it does not keep two writers out of each other's.
+  pub fn exclusive(dir: &Path) -> Result<DirLock, String> {
+    let file = Self::open(dir)?;
+    file.lock_exclusive().map_err(|e| {
+      format!(
+        "Failed to acquire exclusive lock on {}: {}",
+        dir.display(),
+        e
+      )
+    })?;
+    Ok(DirLock { file })
+  }
+
+  fn open(dir: &Path) -> Result<File, String> {
+    fs::create_dir_all(dir)
+      .map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))?;
+    let lock_path = dir.join(".lock");
+    OpenOptions::new()
+      .create(true)
+      .write(true)
+      .open(&lock_path)
+      .map_err(|e| format!("Failed to open lock file {}: {}", lock_path.display(), e))
+  }
+}
+
+impl Drop for DirLock {
+  fn drop(&mut self) {
+    let _ = self.file.unlock();
+  }
+}
@@ -26,9 +26,12 @@
 #![allow(clippy::mutex_atomic)]
 #![type_length_limit = "1257309"]
 
+mod daemon;
+mod file_lock;
+
 use std::collections::{BTreeMap, BTreeSet};
 use std::iter::{FromIterator, Iterator};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::sync::Arc;
 use std::time::Duration;
@@ -36,8 +39,9 @@ use std::time::Duration;
 use fs::{DirectoryDigest, Permissions, RelativePath};
 use hashing::{Digest, Fingerprint};
 use process_execution::{
-  local::KeepSandboxes, CacheContentBehavior, Context, ImmutableInputs, InputDigests, NamedCaches,
-  Platform, ProcessCacheScope, ProcessExecutionStrategy,
+  local::{KeepSandboxes, OutputStreaming},
+  CacheContentBehavior, Context, ImmutableInputs, InputDigests, NamedCaches, Platform,
+  ProcessCacheScope, ProcessExecutionStrategy,
 };
 use prost::Message;
 use protos::gen::build::bazel::remote::execution::v2::{Action, Command};
@@ -53,6 +57,29 @@ struct ProcessMetadata {
   cache_key_gen_version: Option<String>,
 }
 
+/// How to report the result of running the process: human-readable raw stdout/stderr, or a
+/// machine-readable JSON object describing the result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+  Text,
+  Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "text" => Ok(OutputFormat::Text),
+      "json" => Ok(OutputFormat::Json),
+      other => Err(format!(
+        "Unknown --format value {:?}: expected one of `text`, `json`.",
+        other
+      )),
+    }
+  }
+}
+
 #[derive(StructOpt)]
 struct CommandSpec {
   #[structopt(last = true)]
@@ -98,6 +125,12 @@ struct CommandSpec {
 
   #[structopt(long)]
   cache_key_gen_version: Option<String>,
+
+  /// Scheduling priority to set on the REAPI `ExecuteRequest.execution_policy` for remote
+  /// execution. Lower numbers are treated as more urgent by most REAPI schedulers. Has no
+  /// effect on local execution.
+  #[structopt(long)]
+  priority: Option<i32>,
 }
 
 #[derive(StructOpt)]
@@ -164,6 +197,21 @@ struct Opt {
   #[structopt(long)]
   cas_server: Option<String>,
 
+  /// A URL of an object store to use as the CAS, addressed by scheme: `s3://bucket/prefix`,
+  /// `gs://bucket/prefix`, `https://host/prefix`, or `file://path`. A blob with digest
+  /// `<fingerprint>/<size>` is stored at a key derived from its fingerprint. Mutually exclusive
+  /// with `--cas-server`.
+  #[structopt(long)]
+  cas_object_store_url: Option<String>,
+
+  /// A CAS tier to read through and write back to, nearest first: a local LMDB directory path, an
+  /// object-store URL (`s3://`, `gs://`, `http(s)://`, `file://`), or a gRPC `host:port`. May be
+  /// repeated to build a read-through/write-back stack, e.g. a local LMDB tier backed by an
+  /// object-store tier backed by a gRPC CAS tier. Mutually exclusive with `--cas-server` and
+  /// `--cas-object-store-url`, which are shorthands for a single remote tier.
+  #[structopt(long)]
+  cas_tier: Vec<String>,
+
   /// Path to file containing root certificate authority certificates for the CAS server.
   /// If not set, TLS will not be used when connecting to the CAS server.
   #[structopt(long)]
@@ -206,6 +254,30 @@ struct Opt {
   /// Extra header to pass on remote execution request.
   #[structopt(long)]
   header: Vec<String>,
+
+  /// Stream the process' stdout/stderr to our stdout/stderr as they are produced, rather than
+  /// waiting for the process to finish and printing the whole captured output at once. Output is
+  /// still materialized to `--materialize-output-to` as usual once the process completes.
+  #[structopt(long)]
+  stream_output: bool,
+
+  /// Run as a daemon: bind a Unix socket and serve repeated execution requests against a single,
+  /// already-constructed `Store` and `CommandRunner` (remote if `--server` is set, otherwise
+  /// local), rather than exiting after one execution. Use `--daemon-socket` to choose a non-default socket path.
+  #[structopt(long)]
+  daemon: bool,
+
+  /// With `--daemon`, the Unix socket path to listen on. Without `--daemon`, the socket path of
+  /// a running daemon to submit this invocation's request to instead of constructing a
+  /// `CommandRunner` and running the process itself; the client still opens a `Store` to
+  /// materialize and print the results. Defaults to a path in the temp directory.
+  #[structopt(long)]
+  daemon_socket: Option<PathBuf>,
+
+  /// How to report the result: `text` prints raw captured stdout/stderr, `json` prints a single
+  /// JSON object with the exit code, output digests, and execution metadata instead.
+  #[structopt(long, default_value = "text")]
+  format: OutputFormat,
 }
 
 /// A binary which takes args of format:
@@ -232,42 +304,266 @@ async fn main() {
     .clone()
     .unwrap_or_else(Store::default_path);
 
+  // A shared lock, held for the lifetime of the process: concurrent `process_executor`
+  // invocations pointed at the same `--local-store-path` may all hold it at once, while any
+  // holder of an exclusive lock on that directory (such as GC/compaction) is kept out.
+  let _local_store_lock =
+    file_lock::DirLock::shared(&local_store_path).expect("Error locking local store directory");
   let local_only_store =
     Store::local_only(executor.clone(), local_store_path).expect("Error making local store");
-  let store = match (&args.server, &args.cas_server) {
-    (_, Some(cas_server)) => {
-      let root_ca_certs = args
-        .cas_root_ca_cert_file
-        .as_ref()
-        .map(|path| std::fs::read(path).expect("Error reading root CA certs file"));
-
-      let mut headers = BTreeMap::new();
-      if let Some(ref oauth_path) = args.cas_oauth_bearer_token_path {
-        let token =
-          std::fs::read_to_string(oauth_path).expect("Error reading oauth bearer token file");
-        headers.insert(
-          "authorization".to_owned(),
-          format!("Bearer {}", token.trim()),
-        );
+  let store = if !args.cas_tier.is_empty() {
+    if args.cas_server.is_some() || args.cas_object_store_url.is_some() {
+      panic!("Can't combine --cas-tier with --cas-server or --cas-object-store-url");
+    }
+    build_tiered_store(&executor, local_only_store, &args.cas_tier, &args)
+  } else {
+    match (&args.server, &args.cas_server, &args.cas_object_store_url) {
+      (_, Some(_), Some(_)) => {
+        panic!("Can't specify both --cas-server and --cas-object-store-url")
       }
-
-      local_only_store.into_with_remote(
-        cas_server,
-        args.remote_instance_name.clone(),
-        grpc_util::tls::Config::new_without_mtls(root_ca_certs),
-        headers,
+      (_, None, Some(object_store_url)) => local_only_store.into_with_remote_object_store(
+        object_store_url,
         args.upload_chunk_bytes,
-        Duration::from_secs(30),
         args.store_rpc_retries,
         args.store_rpc_concurrency,
-        None,
-        args.store_batch_api_size_limit,
-      )
+      ),
+      (_, Some(cas_server), None) => {
+        let root_ca_certs = args
+          .cas_root_ca_cert_file
+          .as_ref()
+          .map(|path| std::fs::read(path).expect("Error reading root CA certs file"));
+
+        let mut headers = BTreeMap::new();
+        if let Some(ref oauth_path) = args.cas_oauth_bearer_token_path {
+          let token =
+            std::fs::read_to_string(oauth_path).expect("Error reading oauth bearer token file");
+          headers.insert(
+            "authorization".to_owned(),
+            format!("Bearer {}", token.trim()),
+          );
+        }
+
+        local_only_store.into_with_remote(
+          cas_server,
+          args.remote_instance_name.clone(),
+          grpc_util::tls::Config::new_without_mtls(root_ca_certs),
+          headers,
+          args.upload_chunk_bytes,
+          Duration::from_secs(30),
+          args.store_rpc_retries,
+          args.store_rpc_concurrency,
+          None,
+          args.store_batch_api_size_limit,
+        )
+      }
+      (None, None, None) => Ok(local_only_store),
+      (Some(_), None, None) => {
+        panic!("Can't specify --server without --cas-server or --cas-object-store-url")
+      }
+    }
+    .expect("Error making remote store")
+  };
+
+  if args.daemon {
+    if args.stream_output {
+      panic!(
+        "--stream-output has no effect with --daemon: a connected client's stdout/stderr would \
+         be forwarded to this daemon process's own detached stdio, not to the client that \
+         submitted the request, since DaemonResponse only carries digests back over the socket. \
+         Drop --stream-output from the --daemon invocation."
+      );
     }
-    (None, None) => Ok(local_only_store),
-    _ => panic!("Can't specify --server without --cas-server"),
+    let socket_path = args
+      .daemon_socket
+      .clone()
+      .unwrap_or_else(daemon::default_socket_path);
+    let workdir = args.work_dir.clone().unwrap_or_else(std::env::temp_dir);
+
+    // Populated by the local arm below; held for the lifetime of the daemon process.
+    let mut named_cache_lock: Option<file_lock::DirLock> = None;
+    let mut immutable_inputs_lock: Option<file_lock::DirLock> = None;
+    let (runner, execution_strategy): (Arc<dyn process_execution::CommandRunner>, &'static str) =
+      match args.server.clone() {
+        Some(address) => {
+          let root_ca_certs = args
+            .execution_root_ca_cert_file
+            .clone()
+            .map(|path| std::fs::read(path).expect("Error reading root CA certs file"));
+
+          if let Some(oauth_path) = args.execution_oauth_bearer_token_path.clone() {
+            let token = std::fs::read_to_string(oauth_path)
+              .expect("Error reading oauth bearer token file");
+            headers.insert(
+              "authorization".to_owned(),
+              format!("Bearer {}", token.trim()),
+            );
+          }
+
+          let remote_runner = process_execution::remote::CommandRunner::new(
+            &address,
+            args.remote_instance_name.clone(),
+            args.command.cache_key_gen_version.clone(),
+            root_ca_certs.clone(),
+            headers.clone(),
+            store.clone(),
+            Duration::from_secs(args.overall_deadline_secs),
+            Duration::from_millis(100),
+            args.execution_rpc_concurrency,
+            None,
+          )
+          .expect("Failed to make remote command runner");
+
+          let runner: Arc<dyn process_execution::CommandRunner> = Arc::new(
+            process_execution::remote_cache::CommandRunner::new(
+              Arc::new(remote_runner),
+              args.remote_instance_name.clone(),
+              args.command.cache_key_gen_version.clone(),
+              executor.clone(),
+              store.clone(),
+              &address,
+              root_ca_certs,
+              headers.clone(),
+              true,
+              true,
+              process_execution::remote_cache::RemoteCacheWarningsBehavior::Backoff,
+              CacheContentBehavior::Defer,
+              args.cache_rpc_concurrency,
+              Duration::from_secs(2),
+            )
+            .expect("Failed to make remote cache command runner"),
+          );
+          (runner, "remote_execution")
+        }
+        None => {
+          let named_cache_path = args
+            .named_cache_path
+            .clone()
+            .unwrap_or_else(NamedCaches::default_path);
+          // Exclusive, not shared: see the equivalent comment in the one-shot arm below.
+          named_cache_lock = Some(
+            file_lock::DirLock::exclusive(&named_cache_path)
+              .expect("Error locking named cache directory"),
+          );
+          immutable_inputs_lock = Some(
+            file_lock::DirLock::exclusive(&workdir.join("immutable_inputs"))
+              .expect("Error locking immutable inputs directory"),
+          );
+          let runner: Arc<dyn process_execution::CommandRunner> =
+            Arc::new(process_execution::local::CommandRunner::new(
+              store.clone(),
+              executor.clone(),
+              workdir.clone(),
+              NamedCaches::new(named_cache_path),
+              ImmutableInputs::new(store.clone(), &workdir).unwrap(),
+              KeepSandboxes::Never,
+              OutputStreaming::Buffered,
+            ));
+          (runner, "local")
+        }
+      };
+    daemon::serve(&socket_path, store, runner, execution_strategy)
+      .await
+      .expect("process_executor daemon exited with an error");
+    return;
+  }
+
+  if let Some(socket_path) = args.daemon_socket.clone() {
+    if args.stream_output {
+      panic!(
+        "--stream-output has no effect together with --daemon-socket: the daemon forwards \
+         output to its own detached stdio, not to this client's. Drop --stream-output when \
+         submitting a request to a daemon."
+      );
+    }
+    let request = daemon::DaemonRequest {
+      argv: args.command.argv.clone(),
+      env: args.command.env.clone(),
+      input_digest: args
+        .command
+        .input_digest
+        .expect("--daemon-socket requires --input-digest"),
+      input_digest_length: args
+        .command
+        .input_digest_length
+        .expect("--daemon-socket requires --input-digest-length"),
+      output_file_path: args.command.output_file_path.clone(),
+      output_directory_path: args.command.output_directory_path.clone(),
+      working_directory: args.command.working_directory.clone(),
+      jdk: args.command.jdk.clone(),
+      concurrency_available: args.command.concurrency_available,
+      priority: args.command.priority,
+    };
+    let response = daemon::submit(&socket_path, request)
+      .await
+      .expect("Failed to submit request to process_executor daemon");
+
+    if let Some(output) = args.materialize_output_to {
+      let output_directory_digest = Digest::new(
+        Fingerprint::from_hex_string(&response.output_directory_fingerprint)
+          .expect("Daemon returned a malformed output directory fingerprint"),
+        response.output_directory_length,
+      );
+      store
+        .materialize_directory(
+          output,
+          DirectoryDigest::from_persisted_digest(output_directory_digest),
+          Permissions::Writable,
+        )
+        .await
+        .unwrap();
+    }
+
+    // `--stream-output` is rejected above, so the daemon never streamed these to our stdio; load
+    // and print them from the response digests instead.
+    match args.format {
+      OutputFormat::Json => {
+        let json_result = serde_json::json!({
+          "exit_code": response.exit_code,
+          "stdout_digest": {
+            "fingerprint": response.stdout_digest_fingerprint,
+            "size_bytes": response.stdout_digest_length,
+          },
+          "stderr_digest": {
+            "fingerprint": response.stderr_digest_fingerprint,
+            "size_bytes": response.stderr_digest_length,
+          },
+          "output_directory_digest": {
+            "fingerprint": response.output_directory_fingerprint,
+            "size_bytes": response.output_directory_length,
+          },
+          "execution_strategy": response.execution_strategy,
+          "metadata": {
+            "source": response.metadata_source,
+            "total_elapsed_ms": response.metadata_total_elapsed_ms,
+          },
+        });
+        println!("{}", json_result);
+      }
+      OutputFormat::Text => {
+        let stdout_digest = Digest::new(
+          Fingerprint::from_hex_string(&response.stdout_digest_fingerprint)
+            .expect("Daemon returned a malformed stdout fingerprint"),
+          response.stdout_digest_length,
+        );
+        let stderr_digest = Digest::new(
+          Fingerprint::from_hex_string(&response.stderr_digest_fingerprint)
+            .expect("Daemon returned a malformed stderr fingerprint"),
+          response.stderr_digest_length,
+        );
+        let stdout: Vec<u8> = store
+          .load_file_bytes_with(stdout_digest, |bytes| bytes.to_vec())
+          .await
+          .unwrap();
+        let stderr: Vec<u8> = store
+          .load_file_bytes_with(stderr_digest, |bytes| bytes.to_vec())
+          .await
+          .unwrap();
+        print!("{}", String::from_utf8(stdout).unwrap());
+        eprint!("{}", String::from_utf8(stderr).unwrap());
+      }
+    }
+    exit(response.exit_code);
   }
-  .expect("Error making remote store");
 
   let (mut request, process_metadata) = make_request(&store, &args)
     .await
@@ -282,6 +578,9 @@ async fn main() {
   }
   let workdir = args.work_dir.unwrap_or_else(std::env::temp_dir);
 
+  // Populated by the local execution arm below; held for the lifetime of the process.
+  let mut named_cache_lock: Option<file_lock::DirLock> = None;
+  let mut immutable_inputs_lock: Option<file_lock::DirLock> = None;
   let runner: Box<dyn process_execution::CommandRunner> = match args.server {
     Some(address) => {
       let root_ca_certs = args
@@ -335,18 +634,42 @@ async fn main() {
 
       command_runner_box
     }
-    None => Box::new(process_execution::local::CommandRunner::new(
-      store.clone(),
-      executor,
-      workdir.clone(),
-      NamedCaches::new(
-        args
-          .named_cache_path
-          .unwrap_or_else(NamedCaches::default_path),
-      ),
-      ImmutableInputs::new(store.clone(), &workdir).unwrap(),
-      KeepSandboxes::Never,
-    )) as Box<dyn process_execution::CommandRunner>,
+    None => {
+      let named_cache_path = args
+        .named_cache_path
+        .unwrap_or_else(NamedCaches::default_path);
+      // Exclusive, not shared: a shared lock only keeps this process out of a writer's way, it
+      // does not keep two concurrent invocations from both starting to populate the same named
+      // cache entry at once. Held for the lifetime of the process.
+      named_cache_lock = Some(
+        file_lock::DirLock::exclusive(&named_cache_path)
+          .expect("Error locking named cache directory"),
+      );
+      // Likewise for immutable inputs, which `ImmutableInputs` materializes under the workdir.
+      immutable_inputs_lock = Some(
+        file_lock::DirLock::exclusive(&workdir.join("immutable_inputs"))
+          .expect("Error locking immutable inputs directory"),
+      );
+      Box::new(process_execution::local::CommandRunner::new(
+        store.clone(),
+        executor,
+        workdir.clone(),
+        NamedCaches::new(named_cache_path),
+        ImmutableInputs::new(store.clone(), &workdir).unwrap(),
+        KeepSandboxes::Never,
+        if args.stream_output {
+          OutputStreaming::ForwardToStdioAsProduced
+        } else {
+          OutputStreaming::Buffered
+        },
+      )) as Box<dyn process_execution::CommandRunner>
+    }
+  };
+
+  let execution_strategy_description = if args.server.is_some() {
+    "remote_execution"
+  } else {
+    "local"
   };
 
   let result = in_workunit!("process_executor", Level::Info, |workunit| async move {
@@ -357,23 +680,58 @@ async fn main() {
 
   if let Some(output) = args.materialize_output_to {
     store
-      .materialize_directory(output, result.output_directory, Permissions::Writable)
+      .materialize_directory(output, result.output_directory.clone(), Permissions::Writable)
       .await
       .unwrap();
   }
 
-  let stdout: Vec<u8> = store
-    .load_file_bytes_with(result.stdout_digest, |bytes| bytes.to_vec())
-    .await
-    .unwrap();
-
-  let stderr: Vec<u8> = store
-    .load_file_bytes_with(result.stderr_digest, |bytes| bytes.to_vec())
-    .await
-    .unwrap();
-
-  print!("{}", String::from_utf8(stdout).unwrap());
-  eprint!("{}", String::from_utf8(stderr).unwrap());
+  match args.format {
+    OutputFormat::Json => {
+      let output_directory_digest = result.output_directory.as_digest();
+      let json_result = serde_json::json!({
+        "exit_code": result.exit_code,
+        "stdout_digest": {
+          "fingerprint": result.stdout_digest.hash.to_hex(),
+          "size_bytes": result.stdout_digest.size_bytes,
+        },
+        "stderr_digest": {
+          "fingerprint": result.stderr_digest.hash.to_hex(),
+          "size_bytes": result.stderr_digest.size_bytes,
+        },
+        "output_directory_digest": {
+          "fingerprint": output_directory_digest.hash.to_hex(),
+          "size_bytes": output_directory_digest.size_bytes,
+        },
+        "execution_strategy": execution_strategy_description,
+        "metadata": {
+          "source": format!("{:?}", result.metadata.source),
+          "total_elapsed_ms": result.metadata.total_elapsed.map(|d| d.as_millis() as u64),
+        },
+      });
+      println!("{}", json_result);
+    }
+    OutputFormat::Text => {
+      // When `--stream-output` is set, the CommandRunner has already forwarded stdout/stderr
+      // bytes to our stdio as they were produced (locally by tailing the child's pipes, remotely
+      // by polling the REAPI Operation's incremental `stdout_stream`/`stderr_stream`), so there is
+      // nothing left to print here; we still materialize the final digests above for
+      // `--materialize-output-to`.
+      if !args.stream_output {
+        let stdout: Vec<u8> = store
+          .load_file_bytes_with(result.stdout_digest, |bytes| bytes.to_vec())
+          .await
+          .unwrap();
+
+        let stderr: Vec<u8> = store
+          .load_file_bytes_with(result.stderr_digest, |bytes| bytes.to_vec())
+          .await
+          .unwrap();
+
+        print!("{}", String::from_utf8(stdout).unwrap());
+        eprint!("{}", String::from_utf8(stderr).unwrap());
+      }
+    }
+  }
   exit(result.exit_code);
 }
 
@@ -483,6 +841,7 @@ async fn make_request_from_flat_args(
     cache_scope: ProcessCacheScope::Always,
     execution_strategy,
     remote_cache_speculation_delay: Duration::from_millis(0),
+    priority: args.command.priority.unwrap_or(0),
   };
   let metadata = ProcessMetadata {
     instance_name: args.remote_instance_name.clone(),
@@ -578,6 +937,10 @@ async fn extract_request_from_action_digest(
     cache_scope: ProcessCacheScope::Always,
     execution_strategy,
     remote_cache_speculation_delay: Duration::from_millis(0),
+    // `ExecutionPolicy.priority` lives on REAPI's `ExecuteRequest`, not on `Action` itself, and a
+    // bare action-digest invocation never had an `ExecuteRequest` to read it from. Default to 0,
+    // the same as an unset `--priority` on the flat-args path above.
+    priority: 0,
   };
 
   let metadata = ProcessMetadata {
@@ -649,6 +1012,142 @@ async fn extract_request_from_buildbarn_url(
   .await
 }
 
+/// Builds a read-through/write-back tiered store from `--cas-tier` specs, nearest tier first.
+fn build_tiered_store(
+  executor: &task_executor::Executor,
+  local_only_store: Store,
+  tier_specs: &[String],
+  args: &Opt,
+) -> Store {
+  let tiers = tier_specs
+    .iter()
+    .map(|spec| build_cas_tier(executor, &local_only_store, spec, args))
+    .collect::<Result<Vec<_>, String>>()
+    .expect("Error making one of the --cas-tier stores");
+  Store::tiered(tiers)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CasTierKind {
+  ObjectStore,
+  Grpc,
+  LocalPath,
+}
+
+/// Classifies a single `--cas-tier` spec the same way `--cas-object-store-url`/`--cas-server` are
+/// distinguished: any spec containing `://` (such as `s3://`, `gs://`, `http(s)://`, `file://`) is
+/// `ObjectStore`, whatever its scheme, a bare `host:port` that isn't an existing local path is `Grpc`, and anything
+/// else is a local LMDB directory path.
+fn classify_cas_tier_spec(spec: &str) -> CasTierKind {
+  if spec.contains("://") {
+    CasTierKind::ObjectStore
+  } else if spec.contains(':') && !Path::new(spec).exists() {
+    CasTierKind::Grpc
+  } else {
+    CasTierKind::LocalPath
+  }
+}
+
+fn build_cas_tier(
+  executor: &task_executor::Executor,
+  local_only_store: &Store,
+  spec: &str,
+  args: &Opt,
+) -> Result<Store, String> {
+  match classify_cas_tier_spec(spec) {
+    CasTierKind::ObjectStore => local_only_store.clone().into_with_remote_object_store(
+      spec,
+      args.upload_chunk_bytes,
+      args.store_rpc_retries,
+      args.store_rpc_concurrency,
+    ),
+    CasTierKind::Grpc => {
+      let root_ca_certs = args
+        .cas_root_ca_cert_file
+        .as_ref()
+        .map(|path| std::fs::read(path).expect("Error reading root CA certs file"));
+
+      let mut headers = BTreeMap::new();
+      if let Some(ref oauth_path) = args.cas_oauth_bearer_token_path {
+        let token =
+          std::fs::read_to_string(oauth_path).expect("Error reading oauth bearer token file");
+        headers.insert(
+          "authorization".to_owned(),
+          format!("Bearer {}", token.trim()),
+        );
+      }
+
+      local_only_store.clone().into_with_remote(
+        spec,
+        args.remote_instance_name.clone(),
+        grpc_util::tls::Config::new_without_mtls(root_ca_certs),
+        headers,
+        args.upload_chunk_bytes,
+        Duration::from_secs(30),
+        args.store_rpc_retries,
+        args.store_rpc_concurrency,
+        None,
+        args.store_batch_api_size_limit,
+      )
+    }
+    CasTierKind::LocalPath => Store::local_only(executor.clone(), PathBuf::from(spec)),
+  }
+}
+
+#[cfg(test)]
+mod cas_tier_spec_tests {
+  use super::*;
+
+  #[test]
+  fn object_store_schemes() {
+    assert_eq!(
+      classify_cas_tier_spec("s3://bucket/prefix"),
+      CasTierKind::ObjectStore
+    );
+    assert_eq!(
+      classify_cas_tier_spec("gs://bucket/prefix"),
+      CasTierKind::ObjectStore
+    );
+    assert_eq!(
+      classify_cas_tier_spec("https://cas.example.com"),
+      CasTierKind::ObjectStore
+    );
+    assert_eq!(
+      classify_cas_tier_spec("file:///tmp/cas"),
+      CasTierKind::ObjectStore
+    );
+  }
+
+  #[test]
+  fn host_port_is_grpc() {
+    assert_eq!(
+      classify_cas_tier_spec("cas.example.com:443"),
+      CasTierKind::Grpc
+    );
+  }
+
+  #[test]
+  fn bare_local_path_without_colon() {
+    assert_eq!(
+      classify_cas_tier_spec("/var/cas/local-only"),
+      CasTierKind::LocalPath
+    );
+  }
+
+  #[test]
+  fn existing_local_path_containing_a_colon_is_not_grpc() {
+    // `host:port`-shaped specs are ambiguous with a local directory whose name happens to
+    // contain a colon; an existing path on disk wins the tiebreak.
+    let dir = std::env::temp_dir().join("process_executor_cas_tier_spec_test:1234");
+    std::fs::create_dir_all(&dir).unwrap();
+    assert_eq!(
+      classify_cas_tier_spec(dir.to_str().unwrap()),
+      CasTierKind::LocalPath
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
+
 fn collection_from_keyvalues<Str, It, Col>(keyvalues: It) -> Col
 where
   Str: AsRef<str>,